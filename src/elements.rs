@@ -3,83 +3,124 @@
 //! For now, the types only cotain a limited set of the most important
 //! attributes.
 
-use std::{borrow, hash, str};
-use std::collections::{HashMap, HashSet};
+extern crate fnv;
+
+use std::str;
+use std::collections::HashMap;
+use std::collections::hash_map::{Entry, Values};
+use self::fnv::FnvHashMap;
 
 
 //------------ Osm ----------------------------------------------------------
 
 /// An OSM data set.
-/// 
-/// Contains a set each for nodes, ways, and relations.
+///
+/// Contains a map each for nodes, ways, and relations, keyed by their
+/// id.
 pub struct Osm {
-    nodes: HashSet<Node>,
-    ways: HashSet<Way>,
-    relations: HashSet<Relation>,
+    bounds: Option<Bounds>,
+    nodes: FnvHashMap<i64, Node>,
+    ways: FnvHashMap<i64, Way>,
+    relations: FnvHashMap<i64, Relation>,
 }
 
 impl Osm {
     pub fn new() -> Self {
         Osm {
-            nodes: HashSet::new(),
-            ways: HashSet::new(),
-            relations: HashSet::new(),
+            bounds: None,
+            nodes: FnvHashMap::default(),
+            ways: FnvHashMap::default(),
+            relations: FnvHashMap::default(),
         }
     }
 
+    pub fn set_bounds(&mut self, bounds: Bounds) {
+        self.bounds = Some(bounds)
+    }
+
+    /// Adds `node`, keeping the first element seen for a given id.
+    ///
+    /// Returns `false` without replacing the existing node if one with
+    /// the same id is already present, matching the first-wins
+    /// semantics of the `HashSet` this map replaced.
     pub fn add_node(&mut self, node: Node) -> bool {
-        self.nodes.insert(node)
+        match self.nodes.entry(node.id()) {
+            Entry::Occupied(_) => false,
+            Entry::Vacant(entry) => { entry.insert(node); true }
+        }
     }
 
+    /// Adds `way`, keeping the first element seen for a given id.
     pub fn add_way(&mut self, way: Way) -> bool {
-        self.ways.insert(way)
+        match self.ways.entry(way.id()) {
+            Entry::Occupied(_) => false,
+            Entry::Vacant(entry) => { entry.insert(way); true }
+        }
     }
 
+    /// Adds `rel`, keeping the first element seen for a given id.
     pub fn add_relation(&mut self, rel: Relation) -> bool {
-        self.relations.insert(rel)
+        match self.relations.entry(rel.id()) {
+            Entry::Occupied(_) => false,
+            Entry::Vacant(entry) => { entry.insert(rel); true }
+        }
     }
 
     pub fn into_inner(self)
-                      -> (HashSet<Node>, HashSet<Way>, HashSet<Relation>) {
+                      -> (FnvHashMap<i64, Node>, FnvHashMap<i64, Way>,
+                          FnvHashMap<i64, Relation>) {
         (self.nodes, self.ways, self.relations)
     }
 }
 
 impl Osm {
-    pub fn nodes(&self) -> &HashSet<Node> {
-        &self.nodes
+    pub fn bounds(&self) -> Option<&Bounds> {
+        self.bounds.as_ref()
+    }
+
+    /// Returns an iterator over the nodes, in no particular order.
+    ///
+    /// This is a plain iterator, not a set; it offers no lookup by id.
+    pub fn nodes(&self) -> Values<'_, i64, Node> {
+        self.nodes.values()
     }
 
     pub fn has_node(&self, id: i64) -> bool {
-        self.nodes.contains(&id)
+        self.nodes.contains_key(&id)
     }
 
     pub fn get_node(&self, id: i64) -> Option<&Node> {
         self.nodes.get(&id)
     }
 
-    pub fn ways(&self) -> &HashSet<Way> {
-        &self.ways
+    /// Returns an iterator over the ways, in no particular order.
+    ///
+    /// This is a plain iterator, not a set; it offers no lookup by id.
+    pub fn ways(&self) -> Values<'_, i64, Way> {
+        self.ways.values()
     }
 
     pub fn has_way(&self, id: i64) -> bool {
-        self.ways.contains(&id)
+        self.ways.contains_key(&id)
     }
 
     pub fn get_way(&self, id: i64) -> Option<&Way> {
         self.ways.get(&id)
     }
 
-    pub fn relations(&self) -> &HashSet<Relation> {
-        &self.relations
+    /// Returns an iterator over the relations, in no particular order.
+    ///
+    /// This is a plain iterator, not a set; it offers no lookup by id.
+    pub fn relations(&self) -> Values<'_, i64, Relation> {
+        self.relations.values()
     }
 
-    pub fn relations_mut(&mut self) -> &mut HashSet<Relation> {
+    pub fn relations_mut(&mut self) -> &mut FnvHashMap<i64, Relation> {
         &mut self.relations
     }
 
     pub fn has_relation(&self, id: i64) -> bool {
-        self.relations.contains(&id)
+        self.relations.contains_key(&id)
     }
 
     pub fn get_relation(&self, id: i64) -> Option<&Relation> {
@@ -87,6 +128,112 @@ impl Osm {
     }
 }
 
+impl Osm {
+    /// Resolves the ids in a way's node list against this data set.
+    pub fn resolve_way_nodes(&self, way: &Way) -> Vec<Reference<'_>> {
+        way.nodes().iter().map(|&id| {
+            match self.get_node(id) {
+                Some(node) => Reference::Node(node),
+                None => Reference::Unresolved(MemberType::Node, id),
+            }
+        }).collect()
+    }
+
+    /// Resolves a relation's members against this data set.
+    ///
+    /// Returns the member's role alongside its resolved reference.
+    pub fn resolve_members<'a>(&'a self, relation: &'a Relation)
+                               -> Vec<(&'a str, Reference<'a>)> {
+        relation.members().iter().map(|member| {
+            (member.role(), self.resolve_member(member))
+        }).collect()
+    }
+
+    fn resolve_member(&self, member: &Member) -> Reference<'_> {
+        match member.mtype() {
+            MemberType::Node => {
+                match self.get_node(member.id()) {
+                    Some(node) => Reference::Node(node),
+                    None => {
+                        Reference::Unresolved(MemberType::Node, member.id())
+                    }
+                }
+            }
+            MemberType::Way => {
+                match self.get_way(member.id()) {
+                    Some(way) => Reference::Way(way),
+                    None => {
+                        Reference::Unresolved(MemberType::Way, member.id())
+                    }
+                }
+            }
+            MemberType::Relation => {
+                match self.get_relation(member.id()) {
+                    Some(rel) => Reference::Relation(rel),
+                    None => {
+                        Reference::Unresolved(MemberType::Relation,
+                                               member.id())
+                    }
+                }
+            }
+        }
+    }
+}
+
+
+//------------ Reference -----------------------------------------------------
+
+/// A member or node id resolved against an [`Osm`](struct.Osm.html) data
+/// set.
+pub enum Reference<'a> {
+    Node(&'a Node),
+    Way(&'a Way),
+    Relation(&'a Relation),
+
+    /// The referenced id was not present in the data set.
+    Unresolved(MemberType, i64),
+}
+
+
+//------------ Bounds --------------------------------------------------------
+
+/// The geographic extent of an OSM data set.
+pub struct Bounds {
+    minlat: f64,
+    minlon: f64,
+    maxlat: f64,
+    maxlon: f64,
+}
+
+impl Bounds {
+    pub fn new(minlat: f64, minlon: f64, maxlat: f64, maxlon: f64) -> Self {
+        Bounds {
+            minlat: minlat,
+            minlon: minlon,
+            maxlat: maxlat,
+            maxlon: maxlon,
+        }
+    }
+}
+
+impl Bounds {
+    pub fn minlat(&self) -> f64 {
+        self.minlat
+    }
+
+    pub fn minlon(&self) -> f64 {
+        self.minlon
+    }
+
+    pub fn maxlat(&self) -> f64 {
+        self.maxlat
+    }
+
+    pub fn maxlon(&self) -> f64 {
+        self.maxlon
+    }
+}
+
 
 //------------ Node ---------------------------------------------------------
 
@@ -138,27 +285,6 @@ impl Node {
     }
 }
 
-impl borrow::Borrow<i64> for Node {
-    fn borrow(&self) -> &i64 {
-        &self.id
-    }
-}
-
-impl PartialEq for Node {
-    fn eq(&self, other: &Self) -> bool {
-        self.id == other.id
-    }
-}
-
-impl Eq for Node { }
-
-impl hash::Hash for Node {
-    fn hash<H: hash::Hasher>(&self, state: &mut H) {
-        self.id.hash(state)
-    }
-}
-
-
 //------------ Way ----------------------------------------------------------
 
 pub struct Way {
@@ -199,27 +325,6 @@ impl Way {
     }
 }
 
-impl borrow::Borrow<i64> for Way {
-    fn borrow(&self) -> &i64 {
-        &self.id
-    }
-}
-
-impl PartialEq for Way {
-    fn eq(&self, other: &Self) -> bool {
-        self.id == other.id
-    }
-}
-
-impl Eq for Way { }
-
-impl hash::Hash for Way {
-    fn hash<H: hash::Hasher>(&self, state: &mut H) {
-        self.id.hash(state)
-    }
-}
-
-
 //------------ Relation ------------------------------------------------------
 
 pub struct Relation {
@@ -264,27 +369,6 @@ impl Relation {
     }
 }
 
-impl borrow::Borrow<i64> for Relation {
-    fn borrow(&self) -> &i64 {
-        &self.id
-    }
-}
-
-impl PartialEq for Relation {
-    fn eq(&self, other: &Self) -> bool {
-        self.id == other.id
-    }
-}
-
-impl Eq for Relation { }
-
-impl hash::Hash for Relation {
-    fn hash<H: hash::Hasher>(&self, state: &mut H) {
-        self.id.hash(state)
-    }
-}
-
-
 //------------ Member --------------------------------------------------------
 
 pub struct Member {
@@ -0,0 +1,147 @@
+//! Assembling polygon geometry from ways and multipolygon relations.
+//!
+//! OSM areas are encoded either as a single closed way or as a
+//! `type=multipolygon` relation whose member ways (role `outer` or
+//! `inner`) have to be stitched together into closed rings. This module
+//! does that stitching, resolving node ids against an
+//! [`Osm`](../elements/struct.Osm.html) data set along the way.
+
+use ::elements::{MemberType, Osm, Relation, Way};
+
+/// A closed ring of resolved node coordinates, as `(lat, lon)` pairs.
+pub type Ring = Vec<(f64, f64)>;
+
+
+//------------ MultiPolygon --------------------------------------------------
+
+/// The rings assembled from a multipolygon relation or a single way.
+///
+/// `outers` and `inners` are kept as separate, flat lists; matching an
+/// inner ring to the outer ring it forms a hole in (e.g. via a
+/// point-in-polygon test) is left to the caller.
+pub struct MultiPolygon {
+    outers: Vec<Ring>,
+    inners: Vec<Ring>,
+}
+
+impl MultiPolygon {
+    pub fn outers(&self) -> &[Ring] {
+        &self.outers
+    }
+
+    pub fn inners(&self) -> &[Ring] {
+        &self.inners
+    }
+}
+
+
+//------------ PolygonError --------------------------------------------------
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PolygonError {
+    /// A member id referenced by a relation isn't present in the data
+    /// set.
+    MissingMember(MemberType, i64),
+
+    /// The ways of a ring could not be connected into a closed ring.
+    Disconnected,
+}
+
+
+//------------ building rings from ways --------------------------------------
+
+/// Builds a closed ring from a single way.
+///
+/// Returns `Ok(None)` if the way's first and last node ids don't
+/// coincide, i.e. it doesn't describe a closed ring on its own.
+pub fn way_ring(osm: &Osm, way: &Way) -> Result<Option<Ring>, PolygonError> {
+    let nodes = way.nodes();
+    if nodes.is_empty() || nodes.first() != nodes.last() {
+        return Ok(None)
+    }
+    Ok(Some(resolve_ring(osm, nodes)?))
+}
+
+/// Assembles the outer and inner rings of a `type=multipolygon`
+/// relation.
+///
+/// Member ways are connected end to end, reversing a way's node order
+/// when it connects by its end, until each ring closes back on its
+/// first node. Ways are classified by their member role, with any role
+/// other than `inner` treated as `outer`.
+pub fn relation_polygon(osm: &Osm, relation: &Relation)
+                        -> Result<MultiPolygon, PolygonError> {
+    let mut outer_ways = Vec::new();
+    let mut inner_ways = Vec::new();
+    for member in relation.members() {
+        if member.mtype() != MemberType::Way {
+            continue;
+        }
+        let way = match osm.get_way(member.id()) {
+            Some(way) => way,
+            None => {
+                return Err(PolygonError::MissingMember(MemberType::Way,
+                                                         member.id()))
+            }
+        };
+        if member.role() == "inner" {
+            inner_ways.push(way)
+        }
+        else {
+            outer_ways.push(way)
+        }
+    }
+    Ok(MultiPolygon {
+        outers: assemble_rings(osm, outer_ways)?,
+        inners: assemble_rings(osm, inner_ways)?,
+    })
+}
+
+/// Connects a set of ways into closed rings, resolving each ring's node
+/// ids against `osm`.
+fn assemble_rings(osm: &Osm, mut ways: Vec<&Way>)
+                  -> Result<Vec<Ring>, PolygonError> {
+    let mut rings = Vec::new();
+    while !ways.is_empty() {
+        let way = ways.remove(0);
+        let mut nodes = way.nodes().to_vec();
+        if nodes.is_empty() {
+            return Err(PolygonError::Disconnected)
+        }
+        while nodes.first() != nodes.last() {
+            let last = *nodes.last().unwrap();
+            let pos = ways.iter().position(|way| {
+                way.nodes().first() == Some(&last)
+                || way.nodes().last() == Some(&last)
+            });
+            let next = match pos {
+                Some(pos) => ways.remove(pos),
+                None => return Err(PolygonError::Disconnected),
+            };
+            let mut next_nodes = next.nodes().to_vec();
+            if next_nodes.first() == Some(&last) {
+                nodes.extend(next_nodes.drain(1..));
+            }
+            else {
+                next_nodes.reverse();
+                nodes.extend(next_nodes.drain(1..));
+            }
+        }
+        rings.push(resolve_ring(osm, &nodes)?);
+    }
+    Ok(rings)
+}
+
+/// Resolves a ring's node ids into coordinates.
+fn resolve_ring(osm: &Osm, nodes: &[i64]) -> Result<Ring, PolygonError> {
+    let mut ring = Vec::with_capacity(nodes.len());
+    for &id in nodes {
+        match osm.get_node(id) {
+            Some(node) => ring.push((node.lat(), node.lon())),
+            None => {
+                return Err(PolygonError::MissingMember(MemberType::Node, id))
+            }
+        }
+    }
+    Ok(ring)
+}
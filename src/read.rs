@@ -1,9 +1,10 @@
 //! Reading an OSM XML file.
 
 use std::{fmt, io, str};
+use std::result::Result as StdResult;
 use xml::attribute::OwnedAttribute;
 use xml::reader::{Error, EventReader, Result, XmlEvent};
-use ::elements::{Member, Node, Osm, Relation, Way};
+use ::elements::{Bounds, Member, Node, Osm, Relation, Way};
 
 pub fn read_xml<R: io::Read>(source: R) -> Result<Osm> {
     let mut reader = EventReader::new(source);
@@ -15,6 +16,24 @@ pub fn read_xml<R: io::Read>(source: R) -> Result<Osm> {
     read_document(&mut reader)
 }
 
+/// Reads an OSM XML document, dropping malformed elements instead of
+/// aborting the whole parse.
+///
+/// A `node`, `way`, or `relation` whose own attributes or one of its
+/// children fails to parse is skipped entirely; its id (if it could be
+/// determined) and the reason it was dropped are recorded in the
+/// returned [`SkipReport`](struct.SkipReport.html). XML-layer errors
+/// (malformed markup) are not recoverable and still abort the parse.
+pub fn read_xml_lenient<R: io::Read>(source: R) -> Result<(Osm, SkipReport)> {
+    let mut reader = EventReader::new(source);
+    while let XmlEvent::ProcessingInstruction{..} = reader.next()? {
+    }
+    if expect_element(&mut reader, "osm")?.is_none() {
+        panic!("Got unexpected end element event");
+    }
+    read_document_lenient(&mut reader)
+}
+
 fn read_document<R: io::Read>(reader: &mut EventReader<R>) -> Result<Osm> {
     let mut res = Osm::new();
     loop {
@@ -26,6 +45,7 @@ fn read_document<R: io::Read>(reader: &mut EventReader<R>) -> Result<Osm> {
             _ => return Err(Error::from((&*reader, "expected element"))),
         };
         match name.as_ref() {
+            "bounds" => { res.set_bounds(read_bounds(attrs, reader)?); },
             "node" => { res.add_node(read_node(attrs, reader)?); },
             "way" => { res.add_way(read_way(attrs, reader)?); },
             "relation" => { res.add_relation(read_relation(attrs, reader)?); },
@@ -34,8 +54,138 @@ fn read_document<R: io::Read>(reader: &mut EventReader<R>) -> Result<Osm> {
     }
 }
 
+fn read_document_lenient<R: io::Read>(reader: &mut EventReader<R>)
+                                      -> Result<(Osm, SkipReport)> {
+    let mut res = Osm::new();
+    let mut report = SkipReport::new();
+    loop {
+        let (name, attrs) = match reader.next()? {
+            XmlEvent::EndDocument => return Ok((res, report)),
+            XmlEvent::StartElement{name, attributes, ..} => {
+                (name.local_name, attributes)
+            }
+            _ => return Err(Error::from((&*reader, "expected element"))),
+        };
+        match name.as_ref() {
+            "bounds" => {
+                match read_bounds_lenient(attrs, reader)? {
+                    Ok(bounds) => res.set_bounds(bounds),
+                    Err(reason) => report.push("bounds", None, reason),
+                }
+            },
+            "node" => {
+                match read_node_lenient(attrs, reader)? {
+                    Ok(node) => { res.add_node(node); }
+                    Err((id, reason)) => report.push("node", id, reason),
+                }
+            },
+            "way" => {
+                match read_way_lenient(attrs, reader)? {
+                    Ok(way) => { res.add_way(way); }
+                    Err((id, reason)) => report.push("way", id, reason),
+                }
+            },
+            "relation" => {
+                match read_relation_lenient(attrs, reader)? {
+                    Ok(rel) => { res.add_relation(rel); }
+                    Err((id, reason)) => report.push("relation", id, reason),
+                }
+            },
+            _ => { }
+        }
+    }
+}
+
+/// Reads a `bounds` element, aborting the whole parse on a malformed
+/// attribute.
+///
+/// See [`read_node`](fn.read_node.html) for how this relates to
+/// [`read_bounds_lenient`](fn.read_bounds_lenient.html).
+fn read_bounds<R: io::Read>(attrs: Vec<OwnedAttribute>,
+                            reader: &mut EventReader<R>) -> Result<Bounds> {
+    match read_bounds_lenient(attrs, reader)? {
+        Ok(bounds) => Ok(bounds),
+        Err(reason) => Err(Error::from((&*reader, reason))),
+    }
+}
+
+/// Reads a `bounds` element, reporting rather than aborting on a
+/// malformed attribute.
+///
+/// On success, returns `Ok(Ok(bounds))`. If an attribute is missing or
+/// unparseable, the element's subtree is skipped and
+/// `Ok(Err(reason))` is returned. An `Err` at the outer level indicates
+/// an unrecoverable XML-layer error.
+fn read_bounds_lenient<R: io::Read>(attrs: Vec<OwnedAttribute>,
+                                    reader: &mut EventReader<R>)
+                                    -> Result<StdResult<Bounds, String>> {
+    let (mut minlat, mut minlon, mut maxlat, mut maxlon) = (None, None, None, None);
+    for item in attrs {
+        match item.name.local_name.as_ref() {
+            "minlat" => minlat = Some(item.value),
+            "minlon" => minlon = Some(item.value),
+            "maxlat" => maxlat = Some(item.value),
+            "maxlon" => maxlon = Some(item.value),
+            _ => { }
+        }
+    }
+    let minlat = match from_attr(minlat, reader, "minlat") {
+        Ok(minlat) => minlat,
+        Err(err) => {
+            skip_subtree(reader, 0)?;
+            return Ok(Err(format!("{}", err)));
+        }
+    };
+    let minlon = match from_attr(minlon, reader, "minlon") {
+        Ok(minlon) => minlon,
+        Err(err) => {
+            skip_subtree(reader, 0)?;
+            return Ok(Err(format!("{}", err)));
+        }
+    };
+    let maxlat = match from_attr(maxlat, reader, "maxlat") {
+        Ok(maxlat) => maxlat,
+        Err(err) => {
+            skip_subtree(reader, 0)?;
+            return Ok(Err(format!("{}", err)));
+        }
+    };
+    let maxlon = match from_attr(maxlon, reader, "maxlon") {
+        Ok(maxlon) => maxlon,
+        Err(err) => {
+            skip_subtree(reader, 0)?;
+            return Ok(Err(format!("{}", err)));
+        }
+    };
+    while expect_any_element(reader)?.is_some() { }
+    Ok(Ok(Bounds::new(minlat, minlon, maxlat, maxlon)))
+}
+
+/// Reads a `node` element, aborting the whole parse on a malformed
+/// attribute.
+///
+/// This shares its implementation with
+/// [`read_node_lenient`](fn.read_node_lenient.html); it only differs in
+/// what happens once that function has already skipped the offending
+/// subtree.
 fn read_node<R: io::Read>(attrs: Vec<OwnedAttribute>,
                           reader: &mut EventReader<R>) -> Result<Node> {
+    match read_node_lenient(attrs, reader)? {
+        Ok(node) => Ok(node),
+        Err((_, reason)) => Err(Error::from((&*reader, reason))),
+    }
+}
+
+/// Reads a `node` element, dropping it if an attribute is malformed.
+///
+/// On success, returns `Ok(Ok(node))`. If the node or one of its tags
+/// has a missing or unparseable attribute, the node's subtree is
+/// skipped and `Ok(Err((id, reason)))` is returned, with `id` set if
+/// it could be determined before the failure. An `Err` at the outer
+/// level indicates an unrecoverable XML-layer error.
+fn read_node_lenient<R: io::Read>(attrs: Vec<OwnedAttribute>,
+                                  reader: &mut EventReader<R>)
+                                  -> Result<StdResult<Node, (Option<i64>, String)>> {
     let (mut id, mut lat, mut lon) = (None, None, None);
     for item in attrs {
         match item.name.local_name.as_ref() {
@@ -45,21 +195,62 @@ fn read_node<R: io::Read>(attrs: Vec<OwnedAttribute>,
             _ => { }
         }
     }
-    let id = from_attr(id, reader, "id")?;
-    let lat = from_attr(lat, reader, "lat")?;
-    let lon = from_attr(lon, reader, "lon")?;
+    let id = match from_attr(id, reader, "id") {
+        Ok(id) => id,
+        Err(err) => {
+            skip_subtree(reader, 0)?;
+            return Ok(Err((None, format!("{}", err))));
+        }
+    };
+    let lat = match from_attr(lat, reader, "lat") {
+        Ok(lat) => lat,
+        Err(err) => {
+            skip_subtree(reader, 0)?;
+            return Ok(Err((Some(id), format!("{}", err))));
+        }
+    };
+    let lon = match from_attr(lon, reader, "lon") {
+        Ok(lon) => lon,
+        Err(err) => {
+            skip_subtree(reader, 0)?;
+            return Ok(Err((Some(id), format!("{}", err))));
+        }
+    };
     let mut node = Node::new(id, lat, lon);
     while let Some((name, attrs)) = expect_any_element(reader)? {
         if name == "tag" {
-            let (k, v) = read_tag(attrs, reader)?;
-            node.insert_tag(k, v);
+            match read_tag(attrs, reader) {
+                Ok((k, v)) => node.insert_tag(k, v),
+                Err(err) => {
+                    skip_subtree(reader, 1)?;
+                    return Ok(Err((Some(id), format!("{}", err))));
+                }
+            }
         }
     }
-    Ok(node)
+    Ok(Ok(node))
 }
 
+/// Reads a `way` element, aborting the whole parse on a malformed
+/// attribute.
+///
+/// See [`read_node`](fn.read_node.html) for how this relates to
+/// [`read_way_lenient`](fn.read_way_lenient.html).
 fn read_way<R: io::Read>(attrs: Vec<OwnedAttribute>,
                          reader: &mut EventReader<R>) -> Result<Way> {
+    match read_way_lenient(attrs, reader)? {
+        Ok(way) => Ok(way),
+        Err((_, reason)) => Err(Error::from((&*reader, reason))),
+    }
+}
+
+/// Reads a `way` element, dropping it if an attribute is malformed.
+///
+/// See [`read_node_lenient`](fn.read_node_lenient.html) for the result
+/// shape.
+fn read_way_lenient<R: io::Read>(attrs: Vec<OwnedAttribute>,
+                                 reader: &mut EventReader<R>)
+                                 -> Result<StdResult<Way, (Option<i64>, String)>> {
     let mut id = None;
     for item in attrs {
         match item.name.local_name.as_ref() {
@@ -67,26 +258,62 @@ fn read_way<R: io::Read>(attrs: Vec<OwnedAttribute>,
             _ => { }
         }
     }
-    let id = from_attr(id, reader, "id")?;
+    let id = match from_attr(id, reader, "id") {
+        Ok(id) => id,
+        Err(err) => {
+            skip_subtree(reader, 0)?;
+            return Ok(Err((None, format!("{}", err))));
+        }
+    };
     let mut way = Way::new(id);
     while let Some((name, attrs)) = expect_any_element(reader)? {
         match name.as_ref() {
             "nd" => {
-                way.push_node(read_nd(attrs, reader)?);
+                match read_nd(attrs, reader) {
+                    Ok(node_id) => way.push_node(node_id),
+                    Err(err) => {
+                        skip_subtree(reader, 1)?;
+                        return Ok(Err((Some(id), format!("{}", err))));
+                    }
+                }
             }
             "tag" => {
-                let (k, v) = read_tag(attrs, reader)?;
-                way.insert_tag(k, v)
+                match read_tag(attrs, reader) {
+                    Ok((k, v)) => way.insert_tag(k, v),
+                    Err(err) => {
+                        skip_subtree(reader, 1)?;
+                        return Ok(Err((Some(id), format!("{}", err))));
+                    }
+                }
             }
             _ => { }
         }
     }
-    Ok(way)
+    Ok(Ok(way))
 }
 
+/// Reads a `relation` element, aborting the whole parse on a malformed
+/// attribute.
+///
+/// See [`read_node`](fn.read_node.html) for how this relates to
+/// [`read_relation_lenient`](fn.read_relation_lenient.html).
 fn read_relation<R: io::Read>(attrs: Vec<OwnedAttribute>,
                               reader: &mut EventReader<R>)
                               -> Result<Relation> {
+    match read_relation_lenient(attrs, reader)? {
+        Ok(relation) => Ok(relation),
+        Err((_, reason)) => Err(Error::from((&*reader, reason))),
+    }
+}
+
+/// Reads a `relation` element, dropping it if an attribute is malformed.
+///
+/// See [`read_node_lenient`](fn.read_node_lenient.html) for the result
+/// shape.
+fn read_relation_lenient<R: io::Read>(attrs: Vec<OwnedAttribute>,
+                                      reader: &mut EventReader<R>)
+                                      -> Result<StdResult<Relation,
+                                                           (Option<i64>, String)>> {
     let mut id = None;
     for item in attrs {
         match item.name.local_name.as_ref() {
@@ -94,25 +321,40 @@ fn read_relation<R: io::Read>(attrs: Vec<OwnedAttribute>,
             _ => { }
         }
     }
-    let id = from_attr(id, reader, "id")?;
+    let id = match from_attr(id, reader, "id") {
+        Ok(id) => id,
+        Err(err) => {
+            skip_subtree(reader, 0)?;
+            return Ok(Err((None, format!("{}", err))));
+        }
+    };
     let mut relation = Relation::new(id);
     while let Some((name, attrs)) = expect_any_element(reader)? {
         match name.as_ref() {
             "member" => {
-                relation.push_member(read_member(attrs, reader)?);
+                match read_member(attrs, reader) {
+                    Ok(member) => relation.push_member(member),
+                    Err(err) => {
+                        skip_subtree(reader, 1)?;
+                        return Ok(Err((Some(id), format!("{}", err))));
+                    }
+                }
             }
             "tag" => {
-                let (k, v) = read_tag(attrs, reader)?;
-                relation.insert_tag(k, v);
+                match read_tag(attrs, reader) {
+                    Ok((k, v)) => relation.insert_tag(k, v),
+                    Err(err) => {
+                        skip_subtree(reader, 1)?;
+                        return Ok(Err((Some(id), format!("{}", err))));
+                    }
+                }
             }
             _ => { }
         }
     }
-    Ok(relation)
+    Ok(Ok(relation))
 }
 
-
-
 fn read_tag<R: io::Read>(attrs: Vec<OwnedAttribute>,
                          reader: &mut EventReader<R>)
                          -> Result<(String, String)> {
@@ -126,6 +368,7 @@ fn read_tag<R: io::Read>(attrs: Vec<OwnedAttribute>,
     }
     let k = from_attr(k, reader, "k")?;
     let v = from_attr(v, reader, "v")?;
+    while expect_any_element(reader)?.is_some() { }
     Ok((k, v))
 }
 
@@ -139,6 +382,7 @@ fn read_nd<R: io::Read>(attrs: Vec<OwnedAttribute>,
         }
     }
     let id = from_attr(id, reader, "ref")?;
+    while expect_any_element(reader)?.is_some() { }
     Ok(id)
 }
 
@@ -156,6 +400,7 @@ fn read_member<R: io::Read>(attrs: Vec<OwnedAttribute>,
     let mtype = from_attr(mtype, reader, "type")?;
     let id = from_attr(id, reader, "ref")?;
     let role = from_attr(role, reader, "role")?;
+    while expect_any_element(reader)?.is_some() { }
     Ok(Member::new(mtype, id, role))
 }
 
@@ -190,6 +435,33 @@ fn expect_any_element<R: io::Read>(reader: &mut EventReader<R>)
     }
 }
 
+/// Consumes events up to and including the end of the currently open
+/// element.
+///
+/// `depth` is the number of `StartElement`s that have already been
+/// seen without their matching `EndElement`, i.e. `0` if the cursor is
+/// positioned right after the element's own start tag and no child has
+/// been opened yet, or `1` if a single child element is still open.
+fn skip_subtree<R: io::Read>(reader: &mut EventReader<R>, mut depth: u32)
+                             -> Result<()> {
+    loop {
+        match reader.next()? {
+            XmlEvent::StartElement{..} => depth += 1,
+            XmlEvent::EndElement{..} => {
+                if depth == 0 {
+                    return Ok(())
+                }
+                depth -= 1;
+            }
+            XmlEvent::EndDocument => {
+                return Err(Error::from((&*reader,
+                                        "unexpected end of document")))
+            }
+            _ => { }
+        }
+    }
+}
+
 fn from_attr<R, T>(val: Option<String>, reader: &EventReader<R>,
                    attr: &str) -> Result<T>
             where R: io::Read, T: str::FromStr, T::Err: fmt::Display {
@@ -205,3 +477,60 @@ fn from_attr<R, T>(val: Option<String>, reader: &EventReader<R>,
         }
     }
 }
+
+
+//------------ SkipReport ----------------------------------------------------
+
+/// A record of the elements dropped while parsing with
+/// [`read_xml_lenient`](fn.read_xml_lenient.html).
+pub struct SkipReport {
+    dropped: Vec<Dropped>,
+}
+
+impl SkipReport {
+    fn new() -> Self {
+        SkipReport { dropped: Vec::new() }
+    }
+
+    fn push(&mut self, kind: &'static str, id: Option<i64>, reason: String) {
+        self.dropped.push(Dropped { kind: kind, id: id, reason: reason })
+    }
+}
+
+impl SkipReport {
+    pub fn is_empty(&self) -> bool {
+        self.dropped.is_empty()
+    }
+
+    pub fn dropped(&self) -> &[Dropped] {
+        &self.dropped
+    }
+}
+
+
+//------------ Dropped -------------------------------------------------------
+
+/// A single element that was dropped during a lenient parse.
+pub struct Dropped {
+    kind: &'static str,
+    id: Option<i64>,
+    reason: String,
+}
+
+impl Dropped {
+    /// The kind of element that was dropped, e.g. `"node"`.
+    pub fn kind(&self) -> &str {
+        self.kind
+    }
+
+    /// The element's id, if it could be determined before parsing
+    /// failed.
+    pub fn id(&self) -> Option<i64> {
+        self.id
+    }
+
+    /// A human-readable description of why the element was dropped.
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+}